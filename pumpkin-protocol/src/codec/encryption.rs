@@ -0,0 +1,162 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use aes::Aes128;
+use bytes::{Buf, BytesMut};
+use cfb8::{
+    Decryptor, Encryptor,
+    cipher::{AsyncStreamCipher, KeyIvInit},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// The shared secret negotiated during login, also reused as the CFB8 IV per vanilla's protocol.
+pub type SharedSecret = [u8; 16];
+
+/// Upper bound on `EncryptedStream::write_buf`. Once it's this full, `poll_write` stops accepting
+/// new plaintext and reports `Pending` instead, so a slow-reading (or stalled) peer applies real
+/// backpressure rather than letting already-encrypted bytes pile up without limit.
+const MAX_WRITE_BUF_BYTES: usize = 64 * 1024;
+
+/// An `AsyncRead + AsyncWrite` wrapper that transparently applies AES-128-CFB8 to whatever
+/// stream it's built over, so it can be stacked directly under the [`super::frame`] codec: bytes
+/// written by the `Framed` layer are encrypted before reaching the socket, and bytes read from
+/// the socket are decrypted before the decoder ever sees them. Neither the packet codec nor any
+/// `ClientPacket` needs to know encryption is active.
+///
+/// CFB8 is a stream cipher, so the encryptor/decryptor state must advance over every byte
+/// exactly once and in order; buffering (rather than re-deriving) already-processed bytes is what
+/// keeps partial reads/writes from corrupting that state.
+pub struct EncryptedStream<S> {
+    inner: S,
+    encryptor: Encryptor<Aes128>,
+    decryptor: Decryptor<Aes128>,
+    // Ciphertext already handed to `encryptor` but not yet accepted by `inner`.
+    write_buf: BytesMut,
+    // Plaintext already produced by `decryptor` but not yet consumed by the caller's `ReadBuf`.
+    read_buf: BytesMut,
+}
+
+impl<S> EncryptedStream<S> {
+    /// Builds the stream from the shared secret negotiated at login. Vanilla uses the same bytes
+    /// for both the AES key and the CFB8 IV.
+    pub fn new(inner: S, shared_secret: &SharedSecret) -> Self {
+        Self {
+            inner,
+            encryptor: Encryptor::<Aes128>::new(shared_secret.into(), shared_secret.into()),
+            decryptor: Decryptor::<Aes128>::new(shared_secret.into(), shared_secret.into()),
+            write_buf: BytesMut::new(),
+            read_buf: BytesMut::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: AsyncWrite + Unpin> EncryptedStream<S> {
+    /// Pushes as much of `write_buf` into `inner` as it will currently accept.
+    fn poll_drain_write_buf(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write_buf.is_empty() {
+            let this = self.as_mut().get_mut();
+            match Pin::new(&mut this.inner).poll_write(cx, &this.write_buf) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write encrypted bytes to the underlying stream",
+                    )));
+                }
+                Poll::Ready(Ok(written)) => this.write_buf.advance(written),
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for EncryptedStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        // Once the buffer is already saturated, try to drain it before accepting more. We check
+        // (and bail out on `Pending`) *before* touching `buf`, since once its bytes are run
+        // through `encryptor` the cipher state has irreversibly advanced over them — we can't
+        // un-consume them on a later retry, so nothing below this point may return `Pending`.
+        if self.write_buf.len() >= MAX_WRITE_BUF_BYTES {
+            match self.as_mut().poll_drain_write_buf(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => return Poll::Pending,
+            }
+            if self.write_buf.len() >= MAX_WRITE_BUF_BYTES {
+                return Poll::Pending;
+            }
+        }
+
+        // Encrypt the whole call's worth of bytes up front so the cipher state only ever
+        // advances once per plaintext byte, then best-effort push the result toward `inner`;
+        // anything that doesn't fit stays buffered until the next `poll_write`/`poll_flush`.
+        let mut ciphertext = buf.to_vec();
+        self.encryptor.encrypt(&mut ciphertext);
+        self.write_buf.extend_from_slice(&ciphertext);
+
+        match self.as_mut().poll_drain_write_buf(cx) {
+            Poll::Ready(Err(error)) => Poll::Ready(Err(error)),
+            _ => Poll::Ready(Ok(buf.len())),
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.as_mut().poll_drain_write_buf(cx) {
+            Poll::Ready(Ok(())) => {
+                let this = self.get_mut();
+                Pin::new(&mut this.inner).poll_flush(cx)
+            }
+            other => other,
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.as_mut().poll_drain_write_buf(cx) {
+            Poll::Ready(Ok(())) => {
+                let this = self.get_mut();
+                Pin::new(&mut this.inner).poll_shutdown(cx)
+            }
+            other => other,
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for EncryptedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.read_buf.is_empty() {
+            let mut scratch = [0u8; 4096];
+            let mut scratch_buf = ReadBuf::new(&mut scratch);
+            match Pin::new(&mut this.inner).poll_read(cx, &mut scratch_buf) {
+                Poll::Ready(Ok(())) => {
+                    let filled = scratch_buf.filled_mut();
+                    this.decryptor.decrypt(filled);
+                    this.read_buf.extend_from_slice(filled);
+                }
+                other => return other,
+            }
+        }
+
+        let n = buf.remaining().min(this.read_buf.len());
+        buf.put_slice(&this.read_buf[..n]);
+        this.read_buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
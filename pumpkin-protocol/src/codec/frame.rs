@@ -0,0 +1,110 @@
+use std::io;
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{ClientPacket, ser::NetworkWriteExt};
+
+/// Largest body a single frame may declare, matching the largest value vanilla ever sends: a
+/// 3-byte VarInt length prefix tops out at `2^21 - 1`.
+const MAX_PACKET_SIZE: usize = 2_097_151;
+
+/// `Decoder`/`Encoder` pair for the Minecraft frame format: a VarInt byte length followed by
+/// exactly that many bytes of packet body. Wrapping a connection in
+/// `tokio_util::codec::Framed<_, RawPacketCodec>` (see [`PacketFramed`]) replaces manual partial-read
+/// tracking and per-packet length writes with a single `Stream`/`Sink` pair, and leaves room for
+/// compression/encryption to be layered on as additional codecs underneath.
+#[derive(Default)]
+pub struct RawPacketCodec {
+    // Length of the frame currently being read, once its VarInt prefix has been parsed, so a body
+    // that arrives split across multiple `decode` calls doesn't re-parse the prefix each time.
+    pending_len: Option<usize>,
+}
+
+impl Decoder for RawPacketCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let len = match self.pending_len {
+            Some(len) => len,
+            None => {
+                let Some((len, prefix_len)) = peek_var_int(src)? else {
+                    // Not even the length prefix has fully arrived yet.
+                    return Ok(None);
+                };
+                if len > MAX_PACKET_SIZE {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("frame length {len} exceeds the {MAX_PACKET_SIZE} byte limit"),
+                    ));
+                }
+                src.advance(prefix_len);
+                self.pending_len = Some(len);
+                len
+            }
+        };
+
+        if src.len() < len {
+            // Reserve what we already know we'll need so the next read fills in one go.
+            src.reserve(len - src.len());
+            return Ok(None);
+        }
+
+        self.pending_len = None;
+        Ok(Some(src.split_to(len)))
+    }
+}
+
+impl<P: ClientPacket> Encoder<&P> for RawPacketCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, packet: &P, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let mut body = Vec::new();
+        packet
+            .write_packet_data(&mut body)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+
+        let mut prefix = Vec::with_capacity(5);
+        prefix
+            .write_var_int(&(body.len() as i32).into())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+
+        dst.reserve(prefix.len() + body.len());
+        dst.put_slice(&prefix);
+        dst.put_slice(&body);
+        Ok(())
+    }
+}
+
+/// Reads a VarInt from the front of `src` without consuming it, returning the decoded value and
+/// the number of prefix bytes it occupies. Returns `Ok(None)` if `src` doesn't yet hold a
+/// complete VarInt, so the caller can wait for more bytes instead of erroring. A VarInt encoding
+/// an `i32` can never validly need more than 5 bytes, so seeing 5 continuation bytes without a
+/// terminator is a malformed prefix, not a partial one — that returns `Err` rather than `Ok(None)`
+/// so a hostile/corrupt length prefix can't wedge the connection open forever.
+fn peek_var_int(src: &BytesMut) -> io::Result<Option<(usize, usize)>> {
+    let mut value: i32 = 0;
+    for i in 0..5 {
+        let Some(&byte) = src.get(i) else {
+            return Ok(None);
+        };
+        value |= i32::from(byte & 0x7F) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(Some((value as usize, i + 1)));
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "VarInt too big"))
+}
+
+/// A Minecraft connection framed over any `AsyncRead + AsyncWrite`: decodes to raw frame bodies
+/// as a `Stream`, and accepts any [`ClientPacket`] as a `Sink`.
+pub type PacketFramed<S> = tokio_util::codec::Framed<S, RawPacketCodec>;
+
+/// Wraps `io` in the Minecraft frame codec.
+pub fn frame<S>(io: S) -> PacketFramed<S>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite,
+{
+    tokio_util::codec::Framed::new(io, RawPacketCodec::default())
+}
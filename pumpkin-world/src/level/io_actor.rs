@@ -0,0 +1,132 @@
+use std::{collections::HashMap, sync::Arc};
+
+use log::trace;
+use pumpkin_util::math::vector2::Vector2;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::chunk::io::ChunkIO;
+
+use super::LevelFolder;
+
+/// Commands accepted by [`IoActor`]. Saves are coalesced by position in the actor's pending map,
+/// so issuing several saves for the same coordinate before a flush only ever writes the latest
+/// one to disk.
+enum IoCommand<T> {
+    SaveChunk(Vector2<i32>, LevelFolder, T),
+    Flush(oneshot::Sender<()>),
+    Shutdown(oneshot::Sender<()>),
+}
+
+/// A cheaply-cloneable handle to a running [`IoActor`]. This is what `Level` holds and sends
+/// commands through; the actor itself runs on its own task.
+#[derive(Clone)]
+pub struct IoActorHandle<T> {
+    sender: mpsc::UnboundedSender<IoCommand<T>>,
+}
+
+impl<T: Send + 'static> IoActorHandle<T> {
+    /// Queues a chunk to be written to the region files rooted at `folder`. A later call for the
+    /// same position before the next flush replaces this one rather than queuing a second write,
+    /// even if it targets a different (post-shard-migration) folder.
+    pub fn save_chunk(&self, pos: Vector2<i32>, folder: LevelFolder, data: T) {
+        let _ = self
+            .sender
+            .send(IoCommand::SaveChunk(pos, folder, data))
+            .inspect_err(|_| log::error!("IO actor channel closed while queuing a save"));
+    }
+
+    /// Forces the actor to write out everything currently queued, without shutting it down.
+    pub async fn flush(&self) {
+        let (send, recv) = oneshot::channel();
+        if self.sender.send(IoCommand::Flush(send)).is_ok() {
+            let _ = recv.await;
+        }
+    }
+
+    /// Drains every remaining queued save and stops the actor. Awaiting this guarantees every
+    /// chunk handed to `save_chunk` before this call has been written to disk.
+    pub async fn shutdown(&self) {
+        let (send, recv) = oneshot::channel();
+        if self.sender.send(IoCommand::Shutdown(send)).is_ok() {
+            let _ = recv.await;
+        }
+    }
+}
+
+/// A long-lived write-behind actor sitting in front of a `ChunkIO`. Instead of every dirtied
+/// chunk triggering its own ad-hoc `spawn_task` + `save_chunks` call, callers queue saves through
+/// an [`IoActorHandle`] and this actor coalesces redundant writes to the same position before
+/// flushing batches to the underlying `ChunkIO`.
+pub struct IoActor<T> {
+    receiver: mpsc::UnboundedReceiver<IoCommand<T>>,
+    // Keyed by position; each entry also carries the shard folder it should currently be written
+    // through, since a region can migrate to a new storage root between one queued save and flush
+    pending: HashMap<Vector2<i32>, (LevelFolder, T)>,
+    saver: Arc<dyn ChunkIO<Data = T>>,
+}
+
+impl<T: Clone + Send + Sync + 'static> IoActor<T> {
+    pub fn new(saver: Arc<dyn ChunkIO<Data = T>>) -> (Self, IoActorHandle<T>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let actor = Self {
+            receiver,
+            pending: HashMap::new(),
+            saver,
+        };
+        (actor, IoActorHandle { sender })
+    }
+
+    /// Runs the actor loop. Intended to be driven by `Level::spawn_task` for the lifetime of the
+    /// level.
+    pub async fn run(mut self) {
+        while let Some(command) = self.receiver.recv().await {
+            match command {
+                IoCommand::SaveChunk(pos, folder, data) => {
+                    // A newer save for this coordinate simply overwrites the older, still-queued
+                    // one; we only ever want the latest state on disk.
+                    self.pending.insert(pos, (folder, data));
+                }
+                IoCommand::Flush(ack) => {
+                    self.flush_pending().await;
+                    let _ = ack.send(());
+                }
+                IoCommand::Shutdown(ack) => {
+                    // Drain anything still queued before acknowledging so `Level::shutdown` can
+                    // rely on a fully-flushed state rather than a best-effort snapshot write.
+                    self.flush_pending().await;
+                    let _ = ack.send(());
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn flush_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        // Shards mean different positions can currently be owned by different storage roots;
+        // group by root so each batch still goes to the `ChunkIO` in one `save_chunks` call.
+        let mut batches: HashMap<std::path::PathBuf, (LevelFolder, Vec<(Vector2<i32>, T)>)> =
+            HashMap::new();
+        for (pos, (folder, data)) in self.pending.drain() {
+            batches
+                .entry(folder.root_folder.clone())
+                .or_insert_with(|| (folder, Vec::new()))
+                .1
+                .push((pos, data));
+        }
+
+        for (folder, batch) in batches.into_values() {
+            trace!(
+                "IO actor flushing {} coalesced chunk writes under {:?}",
+                batch.len(),
+                folder.root_folder
+            );
+            if let Err(error) = self.saver.save_chunks(&folder, batch).await {
+                log::error!("IO actor failed to flush chunks to disk: {}", error);
+            }
+        }
+    }
+}
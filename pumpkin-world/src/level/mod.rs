@@ -0,0 +1,1136 @@
+use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+
+use dashmap::{DashMap, Entry};
+use log::trace;
+use num_traits::Zero;
+use pumpkin_config::{advanced_config, chunk::ChunkFormat};
+use pumpkin_util::math::{position::BlockPos, vector2::Vector2};
+use tokio::{
+    sync::{Mutex, OwnedMutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard, mpsc},
+    task::{JoinHandle, JoinSet},
+};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+mod io_actor;
+mod shard;
+
+pub use io_actor::{IoActor, IoActorHandle};
+pub use shard::ShardConfig;
+use shard::chunk_to_region;
+
+use crate::{
+    chunk::{
+        ChunkData, ChunkParsingError, ChunkReadingError, ScheduledTick, TickPriority,
+        format::{anvil::AnvilChunkFile, linear::LinearFile},
+        io::{ChunkIO, LoadedData, chunk_file_manager::ChunkFileManager},
+    },
+    generation::{Seed, WorldGenerator, get_world_gen},
+    lock::{LevelLocker, anvil::AnvilLevelLocker},
+    world_info::{
+        LevelData, WorldInfoError, WorldInfoReader, WorldInfoWriter,
+        anvil::{AnvilLevelInfo, LEVEL_DAT_BACKUP_FILE_NAME, LEVEL_DAT_FILE_NAME},
+    },
+};
+
+pub type SyncChunk = Arc<RwLock<ChunkData>>;
+pub type SyncEntityChunk = Arc<RwLock<EntityChunkData>>;
+pub type SyncPoiChunk = Arc<RwLock<PoiChunkData>>;
+
+/// Entities saved in the `entities/r.X.Z.mca` region files for a single chunk position.
+///
+/// This is kept separate from `ChunkData` so a world generator (or a plugin) can populate
+/// entities for a chunk independently from its block data, mirroring vanilla's split between
+/// the block, entity and POI region layers of a dimension.
+#[derive(Clone, Debug, Default)]
+pub struct EntityChunkData {
+    pub position: Vector2<i32>,
+    // TODO: Replace with real entity NBT once entity (de)serialization lands
+    pub entities: Vec<Vec<u8>>,
+}
+
+/// Points of interest saved in the `poi/r.X.Z.mca` region files for a single chunk position.
+///
+/// Kept separate from `ChunkData` for the same reason as `EntityChunkData`.
+#[derive(Clone, Debug, Default)]
+pub struct PoiChunkData {
+    pub position: Vector2<i32>,
+    // TODO: Replace with real POI records (type, position, free ticket count) once POI support lands
+    pub points_of_interest: Vec<Vec<u8>>,
+}
+
+/// The `Level` module provides functionality for working with chunks within or outside a Minecraft world.
+///
+/// Key features include:
+///
+/// - **Chunk Loading:** Efficiently loads chunks from disk.
+/// - **Chunk Caching:** Stores accessed chunks in memory for faster access.
+/// - **Chunk Generation:** Generates new chunks on-demand using a specified `WorldGenerator`.
+///
+/// For more details on world generation, refer to the `WorldGenerator` module.
+pub struct Level {
+    pub seed: Seed,
+    // Shared so any subsystem (spawn point, time, game rules, world border, ...) can read or
+    // mutate world metadata concurrently, instead of only `Level` itself holding it by value
+    level_info: Arc<RwLock<LevelData>>,
+    world_info_writer: Arc<dyn WorldInfoWriter>,
+    level_folder: LevelFolder,
+
+    // Holds this level's spawn chunks, which are always loaded
+    spawn_chunks: Arc<DashMap<Vector2<i32>, SyncChunk>>,
+
+    // Chunks that are paired with chunk watchers. When a chunk is no longer watched, it is removed
+    // from the loaded chunks map and sent to the underlying ChunkIO
+    pub loaded_chunks: Arc<DashMap<Vector2<i32>, SyncChunk>>,
+    // Entities and POIs are loaded/saved in lockstep with their owning block chunk, but are
+    // tracked in their own maps since they live in separate region files on disk
+    pub loaded_entity_chunks: Arc<DashMap<Vector2<i32>, SyncEntityChunk>>,
+    pub loaded_poi_chunks: Arc<DashMap<Vector2<i32>, SyncPoiChunk>>,
+    chunk_watchers: Arc<DashMap<Vector2<i32>, usize>>,
+    // Per-position guards serializing a coordinate's load -> cache -> write -> evict transitions,
+    // so a fetch can never re-cache a stale chunk while an evict/write for the same position is
+    // still in flight. Entries are created on first use and removed once nothing else holds them.
+    chunk_locks: Arc<DashMap<Vector2<i32>, Arc<Mutex<()>>>>,
+
+    chunk_saver: Arc<dyn ChunkIO<Data = SyncChunk>>,
+    entity_saver: Arc<dyn ChunkIO<Data = SyncEntityChunk>>,
+    poi_saver: Arc<dyn ChunkIO<Data = SyncPoiChunk>>,
+    // Write-behind actor that coalesces redundant saves to the same position instead of
+    // dispatching an ad-hoc disk write per dirtied chunk
+    chunk_io_handle: IoActorHandle<SyncChunk>,
+    // Region -> storage root mapping, reconfigurable at runtime via `reconfigure_shards`
+    shard_config: Arc<RwLock<ShardConfig>>,
+    // Tracks which root is currently serving a region's reads/writes. Populated lazily on first
+    // access and only updated once a migration triggered by `reconfigure_shards` has completed,
+    // so an in-flight migration keeps serving from the old root until it's safe to cut over.
+    region_locations: Arc<DashMap<Vector2<i32>, PathBuf>>,
+    world_gen: Arc<dyn WorldGenerator>,
+    // Gets unlocked when dropped
+    // TODO: Make this a trait
+    _locker: Arc<AnvilLevelLocker>,
+    block_ticks: Arc<Mutex<Vec<ScheduledTick>>>,
+    /// Tracks tasks associated with this world instance
+    tasks: TaskTracker,
+    /// Root of this level's cancellation tree. Cancelling it (on shutdown) recursively cancels
+    /// every child token, including the per-fetch-batch and per-position generation tokens below,
+    /// so a single call tears down everything at once. Background tasks spawned off `Level`
+    /// (like the autosave loop) select on `cancelled()` instead of needing a handle back here.
+    pub shutdown_token: CancellationToken,
+    // Per-position children of `shutdown_token`, alive for as long as that coordinate has a
+    // queued-but-not-yet-started generation request. `mark_chunks_as_not_watched`/`clean_chunks`
+    // cancel a position's token as soon as it loses its last watcher so in-flight generation work
+    // for chunks nobody wants anymore gets skipped instead of wasting CPU under load.
+    generation_tokens: Arc<DashMap<Vector2<i32>, CancellationToken>>,
+}
+
+/// How often the background task in [`Level::from_root_folder`] flushes `level.dat` to disk.
+// TODO: make this configurable once world-level settings support it
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Clone)]
+pub struct LevelFolder {
+    pub root_folder: PathBuf,
+    pub region_folder: PathBuf,
+    pub entities_folder: PathBuf,
+    pub poi_folder: PathBuf,
+}
+
+/// Acquires the per-position mutation guard from a `chunk_locks` map, creating one on demand.
+/// Free function (rather than a `Level` method) so it can be called from the detached load/
+/// generate tasks in `fetch_chunks`, which only capture the maps they need, not all of `Level`.
+async fn lock_chunk_position(
+    locks: &DashMap<Vector2<i32>, Arc<Mutex<()>>>,
+    pos: Vector2<i32>,
+) -> OwnedMutexGuard<()> {
+    let lock = locks
+        .entry(pos)
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .value()
+        .clone();
+    lock.lock_owned().await
+}
+
+/// Releases a guard acquired with [`lock_chunk_position`], removing the entry once nothing else
+/// references it so the map doesn't grow unboundedly with positions nobody is touching anymore.
+fn unlock_chunk_position(
+    locks: &DashMap<Vector2<i32>, Arc<Mutex<()>>>,
+    pos: Vector2<i32>,
+    guard: OwnedMutexGuard<()>,
+) {
+    drop(guard);
+    locks.remove_if(&pos, |_, lock| Arc::strong_count(lock) == 1);
+}
+
+/// Rebuilds a `LevelFolder` from just its storage root, used when a region's location is already
+/// known (so we don't need to go through `ShardConfig` to look it up again).
+fn root_to_level_folder(root: PathBuf) -> LevelFolder {
+    LevelFolder {
+        region_folder: root.join("region"),
+        entities_folder: root.join("entities"),
+        poi_folder: root.join("poi"),
+        root_folder: root,
+    }
+}
+
+impl Level {
+    pub fn from_root_folder(root_folder: PathBuf) -> Self {
+        // If we are using an already existing world we want to read the seed from the level.dat, If not we want to check if there is a seed in the config, if not lets create a random one
+        let region_folder = root_folder.join("region");
+        let entities_folder = root_folder.join("entities");
+        let poi_folder = root_folder.join("poi");
+        for folder in [&region_folder, &entities_folder, &poi_folder] {
+            if !folder.exists() {
+                std::fs::create_dir_all(folder).expect("Failed to create region folder");
+            }
+        }
+        let level_folder = LevelFolder {
+            root_folder,
+            region_folder,
+            entities_folder,
+            poi_folder,
+        };
+
+        // if we fail to lock, lets crash ???. maybe not the best solution when we have a large server with many worlds and one is locked.
+        // So TODO
+        let locker = AnvilLevelLocker::look(&level_folder).expect("Failed to lock level");
+
+        // TODO: Load info correctly based on world format type
+        let level_info = AnvilLevelInfo.read_world_info(&level_folder);
+        if let Err(error) = &level_info {
+            match error {
+                // If it doesn't exist, just make a new one
+                WorldInfoError::InfoNotFound => (),
+                WorldInfoError::UnsupportedVersion(version) => {
+                    log::error!("Failed to load world info!, {version}");
+                    log::error!("{}", error);
+                    panic!("Unsupported world data! See the logs for more info.");
+                }
+                e => {
+                    panic!("World Error {}", e);
+                }
+            }
+        } else {
+            let dat_path = level_folder.root_folder.join(LEVEL_DAT_FILE_NAME);
+            if dat_path.exists() {
+                let backup_path = level_folder.root_folder.join(LEVEL_DAT_BACKUP_FILE_NAME);
+                fs::copy(dat_path, backup_path).unwrap();
+            }
+        }
+
+        let level_info = level_info.unwrap_or_default(); // TODO: Improve error handling
+        log::info!(
+            "Loading world with seed: {}",
+            level_info.world_gen_settings.seed
+        );
+
+        let seed = Seed(level_info.world_gen_settings.seed as u64);
+        let world_gen = get_world_gen(seed).into();
+
+        let chunk_saver: Arc<dyn ChunkIO<Data = SyncChunk>> = match advanced_config().chunk.format {
+            //ChunkFormat::Anvil => (Arc::new(AnvilChunkFormat), Arc::new(AnvilChunkFormat)),
+            ChunkFormat::Linear => Arc::new(ChunkFileManager::<LinearFile>::default()),
+            ChunkFormat::Anvil => Arc::new(ChunkFileManager::<AnvilChunkFile>::default()),
+        };
+        // Entities and POIs are always stored in Anvil region files, regardless of the
+        // configured block chunk format
+        let entity_saver: Arc<dyn ChunkIO<Data = SyncEntityChunk>> =
+            Arc::new(ChunkFileManager::<AnvilChunkFile>::default());
+        let poi_saver: Arc<dyn ChunkIO<Data = SyncPoiChunk>> =
+            Arc::new(ChunkFileManager::<AnvilChunkFile>::default());
+
+        let (chunk_io_actor, chunk_io_handle) = IoActor::new(chunk_saver.clone());
+        let shard_config = ShardConfig::single(level_folder.root_folder.clone());
+
+        let level = Self {
+            seed,
+            world_gen,
+            world_info_writer: Arc::new(AnvilLevelInfo),
+            level_folder,
+            chunk_saver,
+            entity_saver,
+            poi_saver,
+            chunk_io_handle,
+            shard_config: Arc::new(RwLock::new(shard_config)),
+            region_locations: Arc::new(DashMap::new()),
+            spawn_chunks: Arc::new(DashMap::new()),
+            loaded_chunks: Arc::new(DashMap::new()),
+            loaded_entity_chunks: Arc::new(DashMap::new()),
+            loaded_poi_chunks: Arc::new(DashMap::new()),
+            chunk_watchers: Arc::new(DashMap::new()),
+            chunk_locks: Arc::new(DashMap::new()),
+            level_info: Arc::new(RwLock::new(level_info)),
+            _locker: Arc::new(locker),
+            tasks: TaskTracker::new(),
+            shutdown_token: CancellationToken::new(),
+            generation_tokens: Arc::new(DashMap::new()),
+            block_ticks: Arc::new(Mutex::new(Vec::new())),
+        };
+        // Drive the IO actor for the lifetime of this level, same as any other background task
+        level.spawn_task(chunk_io_actor.run());
+        // Periodically flush level.dat so a crash doesn't lose world metadata that only
+        // otherwise gets written out in `shutdown`
+        level.spawn_task(Self::autosave_loop(
+            level.level_info.clone(),
+            level.world_info_writer.clone(),
+            level.level_folder.clone(),
+            level.shutdown_token.clone(),
+            AUTOSAVE_INTERVAL,
+        ));
+        level
+    }
+
+    /// Background task that periodically writes `level.dat` through `world_info_writer`,
+    /// rotating the existing backup each time, until `shutdown_token` is cancelled. A
+    /// free-standing function (rather than a method) since it only needs to capture the handful
+    /// of `Arc` fields it touches, not all of `Level`.
+    async fn autosave_loop(
+        level_info: Arc<RwLock<LevelData>>,
+        world_info_writer: Arc<dyn WorldInfoWriter>,
+        level_folder: LevelFolder,
+        shutdown_token: CancellationToken,
+        interval: Duration,
+    ) {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick fires immediately; we don't want to autosave right after world load
+        ticker.tick().await;
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let dat_path = level_folder.root_folder.join(LEVEL_DAT_FILE_NAME);
+                    if dat_path.exists() {
+                        let backup_path = level_folder.root_folder.join(LEVEL_DAT_BACKUP_FILE_NAME);
+                        if let Err(error) = fs::copy(&dat_path, &backup_path) {
+                            log::error!("Failed to rotate level.dat backup during autosave: {}", error);
+                        }
+                    }
+
+                    let snapshot = level_info.read().await.clone();
+                    if let Err(error) = world_info_writer.write_world_info(snapshot, &level_folder) {
+                        log::error!("Autosave failed to write level.dat: {}", error);
+                    } else {
+                        log::debug!("Autosaved level.dat");
+                    }
+                }
+                _ = shutdown_token.cancelled() => {
+                    log::debug!("Stopping autosave task for shutdown");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Read access to the shared, concurrently-mutable world metadata (spawn point, time, game
+    /// rules, world border, ...).
+    pub async fn level_info(&self) -> RwLockReadGuard<'_, LevelData> {
+        self.level_info.read().await
+    }
+
+    /// Write access to the shared world metadata. Changes made here are picked up by the next
+    /// autosave tick and by the final write in `shutdown`.
+    pub async fn level_info_mut(&self) -> RwLockWriteGuard<'_, LevelData> {
+        self.level_info.write().await
+    }
+
+    /// Spawns a task associated with this world. All tasks spawned with this method are awaited
+    /// when the client. This means tasks should complete in a reasonable (no looping) amount of time.
+    pub fn spawn_task<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tasks.spawn(task)
+    }
+
+    pub async fn shutdown(&self) {
+        log::info!("Saving level...");
+
+        // Cancels the autosave loop and every still-pending generation task hanging off this
+        // token, so we're not racing generation work while writing out the chunks below.
+        self.shutdown_token.cancel();
+        self.tasks.close();
+
+        // save all chunks currently in memory
+        let chunks_to_write = self
+            .loaded_chunks
+            .iter()
+            .map(|chunk| (*chunk.key(), chunk.value().clone()))
+            .collect::<Vec<_>>();
+        self.loaded_chunks.clear();
+
+        let entity_chunks_to_write = self
+            .loaded_entity_chunks
+            .iter()
+            .map(|chunk| (*chunk.key(), chunk.value().clone()))
+            .collect::<Vec<_>>();
+        self.loaded_entity_chunks.clear();
+
+        let poi_chunks_to_write = self
+            .loaded_poi_chunks
+            .iter()
+            .map(|chunk| (*chunk.key(), chunk.value().clone()))
+            .collect::<Vec<_>>();
+        self.loaded_poi_chunks.clear();
+
+        // TODO: I think the chunk_saver should be at the server level
+        self.chunk_saver.clear_watched_chunks().await;
+        self.write_chunks(chunks_to_write).await;
+        self.write_entity_chunks(entity_chunks_to_write).await;
+        self.write_poi_chunks(poi_chunks_to_write).await;
+
+        // Drain every save we just queued (and any still pending from earlier activity) before
+        // the IO actor's own task is allowed to finish below. Without this, `tasks.wait()` would
+        // deadlock on an actor that is still waiting on us to tell it to stop.
+        self.chunk_io_handle.shutdown().await;
+
+        log::debug!("Awaiting level tasks");
+        self.tasks.wait().await;
+        log::debug!("Done awaiting level tasks");
+
+        // wait for chunks currently saving in other threads
+        self.chunk_saver.block_and_await_ongoing_tasks().await;
+        self.entity_saver.block_and_await_ongoing_tasks().await;
+        self.poi_saver.block_and_await_ongoing_tasks().await;
+
+        // then lets save the world info
+        let level_info = self.level_info.read().await.clone();
+        let result = self
+            .world_info_writer
+            .write_world_info(level_info, &self.level_folder);
+
+        // Lets not stop the overall save for this
+        if let Err(err) = result {
+            log::error!("Failed to save level.dat: {}", err);
+        }
+    }
+
+    pub fn loaded_chunk_count(&self) -> usize {
+        self.loaded_chunks.len()
+    }
+
+    pub async fn clean_up_log(&self) {
+        self.chunk_saver.clean_up_log().await;
+    }
+
+    pub fn list_cached(&self) {
+        for entry in self.loaded_chunks.iter() {
+            log::debug!("In map: {:?}", entry.key());
+        }
+    }
+
+    /// Marks chunks as "watched" by a unique player. When no players are watching a chunk,
+    /// it is removed from memory. Should only be called on chunks the player was not watching
+    /// before
+    pub async fn mark_chunks_as_newly_watched(&self, chunks: &[Vector2<i32>]) {
+        for chunk in chunks {
+            log::trace!("{:?} marked as newly watched", chunk);
+            match self.chunk_watchers.entry(*chunk) {
+                Entry::Occupied(mut occupied) => {
+                    let value = occupied.get_mut();
+                    if let Some(new_value) = value.checked_add(1) {
+                        *value = new_value;
+                        //log::debug!("Watch value for {:?}: {}", chunk, value);
+                    } else {
+                        log::error!("Watching overflow on chunk {:?}", chunk);
+                    }
+                }
+                Entry::Vacant(vacant) => {
+                    vacant.insert(1);
+                }
+            }
+        }
+
+        self.chunk_saver
+            .watch_chunks(&self.level_folder, chunks)
+            .await;
+    }
+
+    #[inline]
+    pub async fn mark_chunk_as_newly_watched(&self, chunk: Vector2<i32>) {
+        self.mark_chunks_as_newly_watched(&[chunk]).await;
+    }
+
+    /// Marks chunks no longer "watched" by a unique player. When no players are watching a chunk,
+    /// it is removed from memory. Should only be called on chunks the player was watching before
+    pub async fn mark_chunks_as_not_watched(&self, chunks: &[Vector2<i32>]) -> Vec<Vector2<i32>> {
+        let mut chunks_to_clean = Vec::new();
+
+        for chunk in chunks {
+            log::trace!("{:?} marked as no longer watched", chunk);
+            match self.chunk_watchers.entry(*chunk) {
+                Entry::Occupied(mut occupied) => {
+                    let value = occupied.get_mut();
+                    *value = value.saturating_sub(1);
+
+                    if *value == 0 {
+                        occupied.remove_entry();
+                        chunks_to_clean.push(*chunk);
+                    }
+                }
+                Entry::Vacant(_) => {
+                    // This can be:
+                    // - Player disconnecting before all packets have been sent
+                    // - Player moving so fast that the chunk leaves the render distance before it
+                    // is loaded into memory
+                }
+            }
+        }
+
+        // A chunk that just lost its last watcher might still be queued for generation; cancel
+        // its token so that work is skipped instead of populating the cache for no one.
+        for chunk in &chunks_to_clean {
+            if let Some((_, token)) = self.generation_tokens.remove(chunk) {
+                token.cancel();
+            }
+        }
+
+        self.chunk_saver
+            .unwatch_chunks(&self.level_folder, chunks)
+            .await;
+        chunks_to_clean
+    }
+
+    /// Returns whether the chunk should be removed from memory
+    #[inline]
+    pub async fn mark_chunk_as_not_watched(&self, chunk: Vector2<i32>) -> bool {
+        !self.mark_chunks_as_not_watched(&[chunk]).await.is_empty()
+    }
+
+    pub async fn clean_chunks(self: &Arc<Self>, chunks: &[Vector2<i32>]) {
+        // Care needs to be take here because of interweaving case:
+        // 1) Remove chunk from cache
+        // 2) Another player wants same chunk
+        // 3) Load (old) chunk from serializer
+        // 4) Write (new) chunk from serializer
+        // Now outdated chunk data is cached and will be written later
+
+        let positions_with_no_watchers = chunks
+            .iter()
+            .filter(|pos| {
+                // Only chunks that have no entry in the watcher map or have 0 watchers
+                self.chunk_watchers
+                    .get(*pos)
+                    .is_none_or(|count| count.is_zero())
+            })
+            .copied()
+            .collect::<Vec<_>>();
+
+        // Skip any still-queued generation for a position nobody is watching anymore.
+        for pos in &positions_with_no_watchers {
+            if let Some((_, token)) = self.generation_tokens.remove(pos) {
+                token.cancel();
+            }
+        }
+
+        let chunks_with_no_watchers = positions_with_no_watchers
+            .iter()
+            .filter_map(|pos| {
+                self.loaded_chunks
+                    .get(pos)
+                    .map(|chunk| (*pos, chunk.value().clone()))
+            })
+            .collect::<Vec<_>>();
+        // Entities and POIs are evicted in lockstep with their owning block chunk, same as they're
+        // loaded and written, so a crash between two runtime evictions can't leave them growing
+        // unbounded for the life of the server the way only flushing them in `shutdown` would.
+        let entity_chunks_with_no_watchers = positions_with_no_watchers
+            .iter()
+            .filter_map(|pos| {
+                self.loaded_entity_chunks
+                    .get(pos)
+                    .map(|chunk| (*pos, chunk.value().clone()))
+            })
+            .collect::<Vec<_>>();
+        let poi_chunks_with_no_watchers = positions_with_no_watchers
+            .iter()
+            .filter_map(|pos| {
+                self.loaded_poi_chunks
+                    .get(pos)
+                    .map(|chunk| (*pos, chunk.value().clone()))
+            })
+            .collect::<Vec<_>>();
+
+        let level = self.clone();
+        self.spawn_task(async move {
+            let positions_to_remove = positions_with_no_watchers;
+            level.write_chunks(chunks_with_no_watchers).await;
+            level
+                .write_entity_chunks(entity_chunks_with_no_watchers)
+                .await;
+            level.write_poi_chunks(poi_chunks_with_no_watchers).await;
+            // Only after we have written the chunks to the serializer do we remove them from the
+            // cache. Holding this coordinate's guard here closes the race the comment above
+            // describes: a fetch that wants to reload/regenerate this position has to wait for
+            // us to finish evicting it first, instead of racing a stale insert against our write.
+            for pos in positions_to_remove {
+                let guard = level.acquire_chunk_lock(pos).await;
+                // Recheck that there is no one watching before each removal
+                let still_unwatched = || {
+                    level
+                        .chunk_watchers
+                        .get(&pos)
+                        .is_none_or(|count| count.is_zero())
+                };
+                let _ = level.loaded_chunks.remove_if(&pos, |_, _| still_unwatched());
+                let _ = level
+                    .loaded_entity_chunks
+                    .remove_if(&pos, |_, _| still_unwatched());
+                let _ = level
+                    .loaded_poi_chunks
+                    .remove_if(&pos, |_, _| still_unwatched());
+                level.release_chunk_lock(pos, guard);
+            }
+        });
+    }
+
+    pub async fn clean_chunk(self: &Arc<Self>, chunk: &Vector2<i32>) {
+        self.clean_chunks(&[*chunk]).await;
+    }
+
+    pub fn is_chunk_watched(&self, chunk: &Vector2<i32>) -> bool {
+        self.chunk_watchers.get(chunk).is_some()
+    }
+
+    /// Acquires the mutation guard for a single chunk coordinate, creating it if this is the
+    /// first caller to touch that position. Hold the returned guard for the duration of the
+    /// coordinate's load/cache/write/evict critical section, then release it with
+    /// [`Self::release_chunk_lock`].
+    async fn acquire_chunk_lock(&self, pos: Vector2<i32>) -> OwnedMutexGuard<()> {
+        lock_chunk_position(&self.chunk_locks, pos).await
+    }
+
+    /// Drops a mutation guard and, if no other caller is waiting on or holding it, removes it
+    /// from `chunk_locks` so the map doesn't grow unboundedly with stale positions.
+    fn release_chunk_lock(&self, pos: Vector2<i32>, guard: OwnedMutexGuard<()>) {
+        unlock_chunk_position(&self.chunk_locks, pos, guard);
+    }
+
+    /// Resolves the `LevelFolder` that should currently serve reads/writes for a chunk position.
+    /// While its region is mid-migration this keeps returning the root it already lives on; only
+    /// `reconfigure_shards` (via `migrate_region`) flips `region_locations` over once the move is
+    /// complete.
+    async fn level_folder_for_chunk(&self, pos: Vector2<i32>) -> LevelFolder {
+        let region = chunk_to_region(pos);
+        if let Some(root) = self.region_locations.get(&region) {
+            return root_to_level_folder(root.value().clone());
+        }
+
+        let folder = self.shard_config.read().await.level_folder_for_region(region);
+        self.region_locations
+            .entry(region)
+            .or_insert_with(|| folder.root_folder.clone());
+        folder
+    }
+
+    /// Groups chunk positions by the `LevelFolder` that currently owns them, so a batch spanning
+    /// multiple shards can still be handed to a `ChunkIO` one region-root at a time.
+    async fn group_by_shard_folder(
+        &self,
+        positions: &[Vector2<i32>],
+    ) -> Vec<(LevelFolder, Vec<Vector2<i32>>)> {
+        let mut groups: std::collections::HashMap<PathBuf, (LevelFolder, Vec<Vector2<i32>>)> =
+            std::collections::HashMap::new();
+        for pos in positions {
+            let folder = self.level_folder_for_chunk(*pos).await;
+            groups
+                .entry(folder.root_folder.clone())
+                .or_insert_with(|| (folder, Vec::new()))
+                .1
+                .push(*pos);
+        }
+        groups.into_values().collect()
+    }
+
+    /// Same grouping as [`Self::group_by_shard_folder`], but for position-keyed data that's about
+    /// to be written rather than just fetched, so a batch of saves spanning multiple shards (e.g.
+    /// mid-migration) still lands through each root's own `LevelFolder`.
+    async fn group_writes_by_shard_folder<T>(
+        &self,
+        pairs: Vec<(Vector2<i32>, T)>,
+    ) -> Vec<(LevelFolder, Vec<(Vector2<i32>, T)>)> {
+        let mut groups: std::collections::HashMap<PathBuf, (LevelFolder, Vec<(Vector2<i32>, T)>)> =
+            std::collections::HashMap::new();
+        for (pos, data) in pairs {
+            let folder = self.level_folder_for_chunk(pos).await;
+            groups
+                .entry(folder.root_folder.clone())
+                .or_insert_with(|| (folder, Vec::new()))
+                .1
+                .push((pos, data));
+        }
+        groups.into_values().collect()
+    }
+
+    /// Swaps in a new region -> storage root mapping and, for every region we've already touched
+    /// whose owning root changes, kicks off a background migration of that region's files.
+    /// Reads/writes keep going to the old root until each region's migration completes.
+    pub async fn reconfigure_shards(self: &Arc<Self>, new_config: ShardConfig) {
+        let regions_to_check = self
+            .region_locations
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect::<Vec<_>>();
+
+        *self.shard_config.write().await = new_config.clone();
+
+        for (region, current_root) in regions_to_check {
+            let target_root = new_config.level_folder_for_region(region).root_folder;
+            if target_root != current_root {
+                let level = self.clone();
+                self.spawn_task(async move {
+                    level.migrate_region(region, current_root, target_root).await;
+                });
+            }
+        }
+    }
+
+    /// Copies a region's `region`/`entities`/`poi` files to their new owning root, then cuts
+    /// `region_locations` over and removes the stale copies. `region_locations` is only updated
+    /// at the very end, so concurrent reads keep resolving to `from_root` for the whole duration
+    /// of the copy.
+    async fn migrate_region(&self, region: Vector2<i32>, from_root: PathBuf, to_root: PathBuf) {
+        log::info!("Migrating region {:?} from {:?} to {:?}", region, from_root, to_root);
+        let file_name = format!("r.{}.{}.mca", region.x, region.z);
+
+        for sub in ["region", "entities", "poi"] {
+            let to_dir = to_root.join(sub);
+            if let Err(error) = std::fs::create_dir_all(&to_dir) {
+                log::error!("Failed to create shard destination {:?}: {}", to_dir, error);
+                return;
+            }
+
+            let from_file = from_root.join(sub).join(&file_name);
+            if from_file.exists() {
+                if let Err(error) = std::fs::copy(&from_file, to_dir.join(&file_name)) {
+                    log::error!("Failed to migrate region file {:?}: {}", from_file, error);
+                    return;
+                }
+            }
+        }
+
+        // Only now that every file is safely copied do we cut reads/writes over to the new root
+        self.region_locations.insert(region, to_root.clone());
+
+        for sub in ["region", "entities", "poi"] {
+            let stale = from_root.join(sub).join(&file_name);
+            if stale.exists() {
+                let _ = std::fs::remove_file(&stale);
+            }
+        }
+        log::info!("Finished migrating region {:?} to {:?}", region, to_root);
+    }
+
+    pub fn clean_memory(&self) {
+        self.chunk_watchers.retain(|_, watcher| !watcher.is_zero());
+        self.loaded_chunks
+            .retain(|at, _| self.chunk_watchers.get(at).is_some());
+
+        // if the difference is too big, we can shrink the loaded chunks
+        // (1024 chunks is the equivalent to a 32x32 chunks area)
+        if self.chunk_watchers.capacity() - self.chunk_watchers.len() >= 4096 {
+            self.chunk_watchers.shrink_to_fit();
+        }
+
+        // if the difference is too big, we can shrink the loaded chunks
+        // (1024 chunks is the equivalent to a 32x32 chunks area)
+        if self.loaded_chunks.capacity() - self.loaded_chunks.len() >= 4096 {
+            self.loaded_chunks.shrink_to_fit();
+        }
+    }
+
+    pub async fn write_chunks(&self, chunks_to_write: Vec<(Vector2<i32>, SyncChunk)>) {
+        if chunks_to_write.is_empty() {
+            return;
+        }
+        let mut block_ticks = self.block_ticks.lock().await;
+
+        for (coord, chunk) in &chunks_to_write {
+            let mut chunk_data = chunk.write().await;
+            chunk_data.block_ticks.clear();
+            // Only keep ticks that are not saved in the chunk
+            block_ticks.retain(|tick| {
+                let (chunk_coord, _relative_coord) =
+                    tick.block_pos.chunk_and_chunk_relative_position();
+                if chunk_coord == *coord {
+                    chunk_data.block_ticks.push(tick.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        drop(block_ticks);
+
+        trace!("Queuing {} chunks with the IO actor", chunks_to_write.len());
+        // `write_chunks` is called concurrently from `shutdown`'s inline call (in `loaded_chunks`
+        // iteration order) and from the background task `clean_chunks` spawns (in the caller's
+        // eviction order); holding every position's guard open for the whole batch means those
+        // two orderings could acquire an overlapping pair of locks in opposite order and deadlock.
+        // Sorting first imposes one global order on every caller, which rules that out.
+        let mut chunks_to_write = chunks_to_write;
+        chunks_to_write.sort_unstable_by_key(|(pos, _)| (pos.x, pos.z));
+
+        let mut guards = Vec::with_capacity(chunks_to_write.len());
+        for (pos, chunk) in chunks_to_write {
+            // Acquired here and held until the IO actor has actually flushed this save below,
+            // not just queued it. The write-behind actor only durably writes a chunk whenever
+            // `flush_pending` next runs, so releasing the guard right after `save_chunk` would
+            // let a concurrent fetch re-cache the on-disk (stale) copy and have a later eviction
+            // write that stale copy back out once the queued save finally lands.
+            let guard = self.acquire_chunk_lock(pos).await;
+            let folder = self.level_folder_for_chunk(pos).await;
+            self.chunk_io_handle.save_chunk(pos, folder, chunk);
+            guards.push((pos, guard));
+        }
+
+        // Block until every save queued above is durable before releasing their guards.
+        self.chunk_io_handle.flush().await;
+        for (pos, guard) in guards {
+            self.release_chunk_lock(pos, guard);
+        }
+    }
+
+    /// Flushes entity chunks to the `entities/r.X.Z.mca` region files, in lockstep with their
+    /// owning block chunk. Routed through each position's current shard, same as block chunks,
+    /// so a region that's been migrated doesn't have its entities written back to the stale root.
+    pub async fn write_entity_chunks(&self, chunks_to_write: Vec<(Vector2<i32>, SyncEntityChunk)>) {
+        if chunks_to_write.is_empty() {
+            return;
+        }
+
+        trace!("Sending entity chunks to ChunkIO {:}", chunks_to_write.len());
+        for (folder, batch) in self.group_writes_by_shard_folder(chunks_to_write).await {
+            if let Err(error) = self.entity_saver.save_chunks(&folder, batch).await {
+                log::error!("Failed writing entity chunk to disk {}", error);
+            }
+        }
+    }
+
+    /// Flushes POI chunks to the `poi/r.X.Z.mca` region files, in lockstep with their owning
+    /// block chunk. Routed through each position's current shard, same as block chunks, so a
+    /// region that's been migrated doesn't have its POIs written back to the stale root.
+    pub async fn write_poi_chunks(&self, chunks_to_write: Vec<(Vector2<i32>, SyncPoiChunk)>) {
+        if chunks_to_write.is_empty() {
+            return;
+        }
+
+        trace!("Sending POI chunks to ChunkIO {:}", chunks_to_write.len());
+        for (folder, batch) in self.group_writes_by_shard_folder(chunks_to_write).await {
+            if let Err(error) = self.poi_saver.save_chunks(&folder, batch).await {
+                log::error!("Failed writing POI chunk to disk {}", error);
+            }
+        }
+    }
+
+    /// Initializes the spawn chunks to these chunks
+    pub async fn read_spawn_chunks(self: &Arc<Self>, chunks: &[Vector2<i32>]) {
+        let (send, mut recv) = mpsc::unbounded_channel();
+
+        let fetcher = self.fetch_chunks(chunks, send);
+        let handler = async {
+            while let Some((chunk, _)) = recv.recv().await {
+                let pos = chunk.read().await.position;
+                self.spawn_chunks.insert(pos, chunk);
+            }
+        };
+
+        let _ = tokio::join!(fetcher, handler);
+        log::debug!("Read {} chunks as spawn chunks", chunks.len());
+    }
+
+    /// Reads/Generates many chunks in a world
+    /// Note: The order of the output chunks will almost never be in the same order as the order of input chunks
+    pub async fn fetch_chunks(
+        self: &Arc<Self>,
+        chunks: &[Vector2<i32>],
+        channel: mpsc::UnboundedSender<(SyncChunk, bool)>,
+    ) {
+        if chunks.is_empty() {
+            return;
+        }
+
+        let send_chunk =
+            move |is_new: bool,
+                  chunk: SyncChunk,
+                  channel: &mpsc::UnboundedSender<(SyncChunk, bool)>| {
+                let _ = channel
+                    .send((chunk, is_new))
+                    .inspect_err(|err| log::error!("unable to send chunk to channel: {}", err));
+            };
+
+        // First send all chunks that we have cached
+        // We expect best case scenario to have all cached
+        let mut remaining_chunks = Vec::new();
+        for chunk in chunks {
+            // Held across the cache check so it can't interleave with an eviction for this same
+            // position: without it, a fetch landing right after `clean_chunks` removes the entry
+            // (but before its write is durable) would fall through to `remaining_chunks` and
+            // reload the same stale on-disk copy the evict is still writing over.
+            let guard = self.acquire_chunk_lock(*chunk).await;
+            let cached = if let Some(cached) = self.loaded_chunks.get(chunk) {
+                Some(cached.value().clone())
+            } else if let Some(spawn_chunk) = self.spawn_chunks.get(chunk) {
+                // Also clone the arc into the loaded chunks
+                self.loaded_chunks
+                    .insert(*chunk, spawn_chunk.value().clone());
+                Some(spawn_chunk.value().clone())
+            } else {
+                None
+            };
+            self.release_chunk_lock(*chunk, guard);
+
+            match cached {
+                Some(cached_chunk) => send_chunk(false, cached_chunk, &channel),
+                None => remaining_chunks.push(*chunk),
+            }
+        }
+
+        if remaining_chunks.is_empty() {
+            return;
+        }
+
+        // These just pass data between async tasks, each of which do not block on anything, so
+        // these do not need to hold a lot
+        let (load_bridge_send, mut load_bridge_recv) =
+            tokio::sync::mpsc::channel::<LoadedData<SyncChunk, ChunkReadingError>>(16);
+        let (generate_bridge_send, mut generate_bridge_recv) = tokio::sync::mpsc::channel(16);
+
+        let load_channel = channel.clone();
+        let loaded_chunks = self.loaded_chunks.clone();
+        let level_block_ticks = self.block_ticks.clone();
+        let chunk_locks = self.chunk_locks.clone();
+        let handle_load = async move {
+            while let Some(data) = load_bridge_recv.recv().await {
+                match data {
+                    LoadedData::Loaded(chunk) => {
+                        let position = chunk.read().await.position;
+
+                        // Load the block ticks from the chunk
+                        let block_ticks = chunk.read().await.block_ticks.clone();
+                        let mut level_block_ticks = level_block_ticks.lock().await;
+                        level_block_ticks.extend(block_ticks);
+                        drop(level_block_ticks);
+
+                        let guard = lock_chunk_position(&chunk_locks, position).await;
+                        let value = loaded_chunks
+                            .entry(position)
+                            .or_insert(chunk)
+                            .value()
+                            .clone();
+                        unlock_chunk_position(&chunk_locks, position, guard);
+                        send_chunk(false, value, &load_channel);
+                    }
+                    LoadedData::Missing(pos) => generate_bridge_send
+                        .send(pos)
+                        .await
+                        .expect("Failed to send position to generation handler"),
+                    LoadedData::Error((pos, error)) => {
+                        match error {
+                            // this is expected, and is not an error
+                            ChunkReadingError::ChunkNotExist
+                            | ChunkReadingError::ParsingError(
+                                ChunkParsingError::ChunkNotGenerated,
+                            ) => {}
+                            // this is an error, and we should log it
+                            error => {
+                                log::error!(
+                                    "Failed to load chunk at {:?}: {} (regenerating)",
+                                    pos,
+                                    error
+                                );
+                            }
+                        };
+
+                        generate_bridge_send
+                            .send(pos)
+                            .await
+                            .expect("Failed to send position to generation handler");
+                    }
+                }
+            }
+        };
+
+        let loaded_chunks = self.loaded_chunks.clone();
+        let world_gen = self.world_gen.clone();
+        let chunk_locks = self.chunk_locks.clone();
+        let generation_tokens = self.generation_tokens.clone();
+        let shutdown_token = self.shutdown_token.clone();
+        let handle_generate = async move {
+            while let Some(pos) = generate_bridge_recv.recv().await {
+                let loaded_chunks = loaded_chunks.clone();
+                let world_gen = world_gen.clone();
+                let channel = channel.clone();
+                let chunk_locks = chunk_locks.clone();
+                // Child of `shutdown_token` so a full level shutdown cancels every in-flight
+                // generation at once, in addition to `mark_chunks_as_not_watched` cancelling a
+                // single position early if it loses its last watcher before generation starts.
+                let token = shutdown_token.child_token();
+                generation_tokens.insert(pos, token.clone());
+                let generation_tokens = generation_tokens.clone();
+                // Acquired here (the async side) and released on the rayon thread once the
+                // dashmap insertion is done, so no other task can observe a half-generated entry
+                let guard = lock_chunk_position(&chunk_locks, pos).await;
+                rayon::spawn(move || {
+                    generation_tokens.remove(&pos);
+
+                    if token.is_cancelled() {
+                        unlock_chunk_position(&chunk_locks, pos, guard);
+                        return;
+                    }
+
+                    let result = loaded_chunks
+                        .entry(pos)
+                        .or_insert_with(|| {
+                            // Avoid possible duplicating work by doing this within the dashmap lock
+                            let generated_chunk = world_gen.generate_chunk(pos);
+                            Arc::new(RwLock::new(generated_chunk))
+                        })
+                        .value()
+                        .clone();
+                    unlock_chunk_position(&chunk_locks, pos, guard);
+
+                    send_chunk(true, result, &channel);
+                });
+            }
+        };
+
+        let mut set = JoinSet::new();
+        set.spawn(handle_load);
+        set.spawn(handle_generate);
+
+        // A batch can span multiple shards if their regions have been migrated to different
+        // roots, so fetch each shard's positions through its own `LevelFolder`
+        for (folder, shard_positions) in self.group_by_shard_folder(&remaining_chunks).await {
+            self.chunk_saver
+                .fetch_chunks(&folder, &shard_positions, load_bridge_send.clone())
+                .await;
+        }
+        let _ = set.join_all().await;
+
+        // Entities and POIs have no generator of their own; a missing region entry just means
+        // an empty chunk, so we load them in lockstep with (but independently of) block data
+        self.fetch_entity_chunks(&remaining_chunks).await;
+        self.fetch_poi_chunks(&remaining_chunks).await;
+    }
+
+    /// Loads entity chunks from the `entities/r.X.Z.mca` region files into `loaded_entity_chunks`.
+    /// Missing entries are populated with an empty `EntityChunkData` so a future generator has
+    /// somewhere to write newly spawned entities.
+    async fn fetch_entity_chunks(self: &Arc<Self>, positions: &[Vector2<i32>]) {
+        let (send, mut recv) = tokio::sync::mpsc::channel(16);
+        let loaded_entity_chunks = self.loaded_entity_chunks.clone();
+        let handler = async move {
+            while let Some(data) = recv.recv().await {
+                match data {
+                    LoadedData::Loaded(chunk) => {
+                        let position = chunk.read().await.position;
+                        loaded_entity_chunks.entry(position).or_insert(chunk);
+                    }
+                    LoadedData::Missing(pos) | LoadedData::Error((pos, _)) => {
+                        loaded_entity_chunks.entry(pos).or_insert_with(|| {
+                            Arc::new(RwLock::new(EntityChunkData {
+                                position: pos,
+                                entities: Vec::new(),
+                            }))
+                        });
+                    }
+                }
+            }
+        };
+
+        // Same shard-spanning caveat as `fetch_chunks`: each position may currently be owned by a
+        // different root, so fetch one shard's worth at a time through its own `LevelFolder`.
+        let fetcher = async {
+            for (folder, shard_positions) in self.group_by_shard_folder(positions).await {
+                self.entity_saver
+                    .fetch_chunks(&folder, &shard_positions, send.clone())
+                    .await;
+            }
+        };
+        let _ = tokio::join!(fetcher, handler);
+    }
+
+    /// Loads POI chunks from the `poi/r.X.Z.mca` region files into `loaded_poi_chunks`. Missing
+    /// entries are populated with an empty `PoiChunkData` so a future generator has somewhere to
+    /// write newly discovered points of interest.
+    async fn fetch_poi_chunks(self: &Arc<Self>, positions: &[Vector2<i32>]) {
+        let (send, mut recv) = tokio::sync::mpsc::channel(16);
+        let loaded_poi_chunks = self.loaded_poi_chunks.clone();
+        let handler = async move {
+            while let Some(data) = recv.recv().await {
+                match data {
+                    LoadedData::Loaded(chunk) => {
+                        let position = chunk.read().await.position;
+                        loaded_poi_chunks.entry(position).or_insert(chunk);
+                    }
+                    LoadedData::Missing(pos) | LoadedData::Error((pos, _)) => {
+                        loaded_poi_chunks.entry(pos).or_insert_with(|| {
+                            Arc::new(RwLock::new(PoiChunkData {
+                                position: pos,
+                                points_of_interest: Vec::new(),
+                            }))
+                        });
+                    }
+                }
+            }
+        };
+
+        // Same shard-spanning caveat as `fetch_chunks`: each position may currently be owned by a
+        // different root, so fetch one shard's worth at a time through its own `LevelFolder`.
+        let fetcher = async {
+            for (folder, shard_positions) in self.group_by_shard_folder(positions).await {
+                self.poi_saver
+                    .fetch_chunks(&folder, &shard_positions, send.clone())
+                    .await;
+            }
+        };
+        let _ = tokio::join!(fetcher, handler);
+    }
+
+    pub fn try_get_chunk(
+        &self,
+        coordinates: Vector2<i32>,
+    ) -> Option<dashmap::mapref::one::Ref<'_, Vector2<i32>, Arc<RwLock<ChunkData>>>> {
+        self.loaded_chunks.try_get(&coordinates).try_unwrap()
+    }
+
+    pub async fn get_and_tick_block_ticks(&self) -> Vec<ScheduledTick> {
+        let mut block_ticks = self.block_ticks.lock().await;
+        let mut ticks = Vec::new();
+        let mut remaining_ticks = Vec::new();
+        for mut tick in block_ticks.drain(..) {
+            tick.delay = tick.delay.saturating_sub(1);
+            if tick.delay == 0 {
+                ticks.push(tick);
+            } else {
+                remaining_ticks.push(tick);
+            }
+        }
+
+        *block_ticks = remaining_ticks;
+        ticks.sort_by_key(|tick| tick.priority);
+        ticks
+    }
+
+    pub async fn is_block_tick_scheduled(&self, block_pos: &BlockPos, block_id: u16) -> bool {
+        let block_ticks = self.block_ticks.lock().await;
+        block_ticks
+            .iter()
+            .any(|tick| tick.block_pos == *block_pos && tick.target_block_id == block_id)
+    }
+
+    pub async fn schedule_block_tick(
+        &self,
+        block_id: u16,
+        block_pos: BlockPos,
+        delay: u16,
+        priority: TickPriority,
+    ) {
+        let mut block_ticks = self.block_ticks.lock().await;
+        block_ticks.push(ScheduledTick {
+            block_pos,
+            delay,
+            priority,
+            target_block_id: block_id,
+        });
+    }
+}
@@ -0,0 +1,72 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use pumpkin_util::math::vector2::Vector2;
+
+use super::LevelFolder;
+
+/// Maps a region coordinate (a chunk coordinate right-shifted by 5, matching vanilla's 32x32
+/// chunk span per `.mca` file) to the storage root that owns it. Lets an operator spread a large
+/// world's region files across more than one disk instead of committing everything to one path.
+#[derive(Clone, Debug)]
+pub struct ShardConfig {
+    /// Storage roots available to shard across. Each gets its own `region`/`entities`/`poi`
+    /// subdirectories, same as today's single world folder.
+    pub roots: Vec<PathBuf>,
+    /// Explicit region -> root index pins, checked before falling back to the hash-based
+    /// default. This is how an operator rebalances specific regions onto a newly added disk.
+    pub overrides: HashMap<Vector2<i32>, usize>,
+}
+
+impl ShardConfig {
+    /// A single-root configuration that behaves exactly like an unsharded world.
+    pub fn single(root: PathBuf) -> Self {
+        Self {
+            roots: vec![root],
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Resolves which root index owns a region coordinate.
+    pub fn root_index_for_region(&self, region: Vector2<i32>) -> usize {
+        if let Some(index) = self.overrides.get(&region) {
+            // An override is only trusted if it still points inside `roots`. `ShardConfig` is
+            // built from an arbitrary runtime reconfiguration, so a stale override left over from
+            // a larger root set must fall back to the hash-based default instead of indexing
+            // `roots` out of bounds.
+            if *index < self.roots.len() {
+                return *index;
+            }
+            log::warn!(
+                "Shard override for region {:?} points at root {} but only {} roots are configured; falling back to the default placement",
+                region,
+                index,
+                self.roots.len()
+            );
+        }
+        if self.roots.len() <= 1 {
+            return 0;
+        }
+        // A simple, deterministic spread; operators needing exact placement use `overrides`.
+        let hash = (region.x as i64).wrapping_mul(341_873_128_712)
+            ^ (region.z as i64).wrapping_mul(132_897_987_541);
+        (hash.unsigned_abs() as usize) % self.roots.len()
+    }
+
+    /// Builds the `LevelFolder` a region coordinate's chunks should currently be read/written
+    /// through under this configuration.
+    pub fn level_folder_for_region(&self, region: Vector2<i32>) -> LevelFolder {
+        let root = self.roots[self.root_index_for_region(region)].clone();
+        LevelFolder {
+            region_folder: root.join("region"),
+            entities_folder: root.join("entities"),
+            poi_folder: root.join("poi"),
+            root_folder: root,
+        }
+    }
+}
+
+/// The region a chunk coordinate belongs to: vanilla packs a 32x32 span of chunks into one
+/// `.mca` file, so this is the chunk coordinate right-shifted by 5.
+pub fn chunk_to_region(pos: Vector2<i32>) -> Vector2<i32> {
+    Vector2::new(pos.x >> 5, pos.z >> 5)
+}
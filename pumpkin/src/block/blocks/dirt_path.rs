@@ -12,12 +12,14 @@ use pumpkin_protocol::server::play::SUseItemOn;
 use pumpkin_util::math::position::BlockPos;
 use pumpkin_world::block::BlockDirection;
 use pumpkin_world::chunk::TickPriority;
+use tracing::instrument;
 
 #[pumpkin_block("minecraft:dirt_path")]
 pub struct DirtPathBlock;
 
 #[async_trait]
 impl PumpkinBlock for DirtPathBlock {
+    #[instrument(skip(self, world, _block), fields(pos = ?pos))]
     async fn on_scheduled_tick(&self, world: &Arc<World>, _block: &Block, pos: &BlockPos) {
         // TODO: push up entities
         world
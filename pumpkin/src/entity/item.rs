@@ -14,6 +14,7 @@ use crate::server::Server;
 
 use crate::plugin::player::player_pickup_item::PlayerPickupItemEvent;
 use pumpkin_macros::send_cancellable;
+use tracing::instrument;
 
 use super::{Entity, EntityBase, living::LivingEntity, player::Player};
 
@@ -55,6 +56,7 @@ impl ItemEntity {
 
 #[async_trait]
 impl EntityBase for ItemEntity {
+    #[instrument(skip_all, fields(entity_id = self.entity.entity_id))]
     async fn tick(&self, server: &Server) {
         self.entity.tick(server).await;
         {
@@ -73,6 +75,7 @@ impl EntityBase for ItemEntity {
         false
     }
 
+    #[instrument(skip_all, fields(entity_id = self.entity.entity_id, player_entity_id = player.entity_id()))]
     async fn on_player_collision(&self, player: Arc<Player>) {
         let can_pickup = {
             let delay = self.pickup_delay.lock().await;
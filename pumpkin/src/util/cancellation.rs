@@ -0,0 +1,53 @@
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+/// Root of the server's shutdown tree: one [`CancellationToken`] that every world, and every
+/// entity/block-tick future spawned under a world, ultimately descends from, paired with the
+/// [`TaskTracker`] used to await all of that spawned work before the process exits.
+///
+/// Cancelling [`Self::token`] recursively cancels every child derived from it (per-`World`
+/// tokens, and the per-tick leaf tokens those in turn hand out), so a tick loop can simply
+/// `select!` between doing work and its own token firing without needing a handle back to the
+/// server. Creating a child of an already-cancelled token immediately returns a cancelled token,
+/// so worlds/tasks spun up mid-shutdown don't miss the signal.
+pub struct ShutdownCoordinator {
+    token: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        Self {
+            token: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+        }
+    }
+
+    /// The root cancellation token. Hand `child_token()` of this down to each `World`.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Spawns a task tracked for graceful shutdown; `shutdown` won't return until every task
+    /// spawned this way has finished.
+    pub fn spawn_task<F>(&self, task: F) -> tokio::task::JoinHandle<F::Output>
+    where
+        F: std::future::Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tasks.spawn(task)
+    }
+
+    /// Cancels the root token (and therefore every world/tick token derived from it), then
+    /// drains every task spawned through [`Self::spawn_task`] before returning.
+    pub async fn shutdown(&self) {
+        self.token.cancel();
+        self.tasks.close();
+        self.tasks.wait().await;
+    }
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
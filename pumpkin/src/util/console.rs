@@ -0,0 +1,17 @@
+//! Optional live task console for the tick loop, built on `tokio-console`. Gated behind the
+//! `tokio-console` feature (add it to `pumpkin`'s `Cargo.toml` alongside the `console-subscriber`
+//! dependency) so a developer can opt in without every build paying for instrumentation they
+//! don't need.
+
+/// Installs the `tracing` subscriber that feeds `tokio-console`, so task spawn/wake/park events
+/// and the `#[instrument]` spans on the tick hot paths (`EntityBase::tick`,
+/// `ItemEntity::on_player_collision`, `DirtPathBlock::on_scheduled_tick`, ...) become visible to
+/// an attached console client. Call once, early in startup, before any tracked task is spawned.
+#[cfg(feature = "tokio-console")]
+pub fn init() {
+    console_subscriber::init();
+}
+
+/// No-op when the feature isn't enabled, so call sites don't need to `#[cfg]` the call itself.
+#[cfg(not(feature = "tokio-console"))]
+pub fn init() {}
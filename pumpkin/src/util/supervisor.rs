@@ -0,0 +1,200 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use dashmap::DashMap;
+use tokio::{sync::Mutex, task::AbortHandle};
+
+/// Identifies a supervised group, e.g. `"world:overworld"` or `"entity-tick"`. Plain string
+/// wrapper rather than an enum since the set of groups is open-ended (one per world, one per
+/// long-lived subsystem) and operators should be able to name their own.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GroupId(String);
+
+impl<S: Into<String>> From<S> for GroupId {
+    fn from(value: S) -> Self {
+        Self(value.into())
+    }
+}
+
+impl std::fmt::Display for GroupId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// What the supervisor does when a task in a group panics or otherwise exits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Restart only the task that died.
+    OneForOne,
+    /// Restart every task currently registered in the group.
+    AllForOne,
+}
+
+type SupervisedFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+type TaskFactory = Arc<dyn Fn() -> SupervisedFuture + Send + Sync>;
+
+struct Group {
+    policy: RestartPolicy,
+    factories: Mutex<Vec<TaskFactory>>,
+    handles: Mutex<Vec<AbortHandle>>,
+    live: AtomicUsize,
+    shutting_down: AtomicBool,
+}
+
+/// A small supervision tree for long-lived async work (per-`World` tick drivers, per-entity
+/// tickers, chunk generation workers, ...). Tasks are registered under a [`GroupId`] along with
+/// the closure that produces them; if a task panics or exits, the supervisor logs the failure
+/// with its group id and re-spawns according to that group's [`RestartPolicy`] instead of the
+/// loop silently disappearing.
+#[derive(Clone)]
+pub struct Supervisor {
+    groups: Arc<DashMap<GroupId, Arc<Group>>>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self {
+            groups: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Registers and spawns a task under `group`. `make_task` is called again every time this
+    /// task (or, under [`RestartPolicy::AllForOne`], any sibling in the group) needs restarting,
+    /// so it must be able to build a fresh future from scratch each time.
+    pub fn supervise<I, F, Fut>(&self, group: I, policy: RestartPolicy, make_task: F)
+    where
+        I: Into<GroupId>,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let group_id = group.into();
+        let factory: TaskFactory = Arc::new(move || Box::pin(make_task()));
+
+        let group_handle = self
+            .groups
+            .entry(group_id.clone())
+            .or_insert_with(|| {
+                Arc::new(Group {
+                    policy,
+                    factories: Mutex::new(Vec::new()),
+                    handles: Mutex::new(Vec::new()),
+                    live: AtomicUsize::new(0),
+                    shutting_down: AtomicBool::new(false),
+                })
+            })
+            .clone();
+
+        let factory_for_group = factory.clone();
+        let groups = self.groups.clone();
+        tokio::spawn(async move {
+            group_handle.factories.lock().await.push(factory_for_group);
+            spawn_supervised(groups, group_id, group_handle, factory).await;
+        });
+    }
+
+    /// Current groups and how many tasks are presently running in each.
+    pub fn groups(&self) -> Vec<(GroupId, usize)> {
+        self.groups
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().live.load(Ordering::SeqCst)))
+            .collect()
+    }
+
+    /// Stops all tasks in `group` and prevents it from restarting anything further. Already-dead
+    /// groups (or ones that were never registered) are a no-op.
+    pub async fn shutdown_group(&self, group: &GroupId) {
+        if let Some((_, group)) = self.groups.remove(group) {
+            group.shutting_down.store(true, Ordering::SeqCst);
+            for handle in group.handles.lock().await.drain(..) {
+                handle.abort();
+            }
+        }
+    }
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Spawns `factory()` as a tracked child task and waits on it; once it finishes normally or
+/// panics, the outcome is logged and, unless the group was shut down in the meantime, the
+/// configured [`RestartPolicy`] is applied. A task that is cancelled (aborted) out from under it
+/// is neither logged nor restarted.
+fn spawn_supervised(
+    groups: Arc<DashMap<GroupId, Arc<Group>>>,
+    group_id: GroupId,
+    group: Arc<Group>,
+    factory: TaskFactory,
+) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        group.live.fetch_add(1, Ordering::SeqCst);
+        let child = tokio::spawn(factory());
+        {
+            // Prune handles for siblings that have already finished (normally or via abort)
+            // before adding this one, so `handles` doesn't grow by one entry per restart for the
+            // life of the group.
+            let mut handles = group.handles.lock().await;
+            handles.retain(|handle| !handle.is_finished());
+            handles.push(child.abort_handle());
+        }
+
+        let result = child.await;
+        group.live.fetch_sub(1, Ordering::SeqCst);
+
+        if group.shutting_down.load(Ordering::SeqCst) {
+            return;
+        }
+
+        match result {
+            Ok(()) => {
+                log::warn!(
+                    "Supervised task in group '{}' exited; restarting per {:?}",
+                    group_id,
+                    group.policy
+                );
+            }
+            Err(join_error) if join_error.is_cancelled() => {
+                // Cancelled by `shutdown_group` (or an abort elsewhere); don't restart.
+                return;
+            }
+            Err(join_error) => {
+                log::error!(
+                    "Supervised task in group '{}' panicked: {}; restarting per {:?}",
+                    group_id,
+                    join_error,
+                    group.policy
+                );
+            }
+        }
+
+        match group.policy {
+            RestartPolicy::OneForOne => {
+                spawn_supervised(groups, group_id, group, factory).await;
+            }
+            RestartPolicy::AllForOne => {
+                // Stop every still-running sibling before respawning the whole group, otherwise
+                // each one gets a fresh task spawned alongside it instead of actually restarting.
+                for handle in group.handles.lock().await.drain(..) {
+                    handle.abort();
+                }
+
+                let factories = group.factories.lock().await.clone();
+                for sibling_factory in factories {
+                    let groups = groups.clone();
+                    let group_id = group_id.clone();
+                    let group = group.clone();
+                    tokio::spawn(spawn_supervised(groups, group_id, group, sibling_factory));
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,115 @@
+use std::{fmt::Debug, future::Future, hash::Hash};
+
+use dashmap::DashMap;
+use tokio::{
+    sync::Mutex,
+    task::{AbortHandle, Id, JoinSet},
+};
+
+/// A `JoinMap`-style registry tying a domain key (an entity id, a player uuid, ...) to the task
+/// currently running its async work. Gives a world a single authoritative place to cancel or
+/// await one entity/player's work by key instead of ad-hoc bookkeeping scattered across the tick
+/// loop and `remove()` calls.
+///
+/// Spawning under a key that's already registered cancels the old task first, so a second
+/// `spawn()` for the same id always wins rather than leaving two tasks racing over the same
+/// entity.
+pub struct KeyedTaskMap<K> {
+    handles: DashMap<K, AbortHandle>,
+    // Maps a task's tokio::task::Id back to the domain key, since a task's own output doesn't
+    // carry it back out on panic/cancellation the way a normal return value would.
+    ids: DashMap<Id, K>,
+    set: Mutex<JoinSet<()>>,
+}
+
+impl<K> KeyedTaskMap<K>
+where
+    K: Eq + Hash + Clone + Debug + Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            handles: DashMap::new(),
+            ids: DashMap::new(),
+            set: Mutex::new(JoinSet::new()),
+        }
+    }
+
+    /// Spawns `task` under `key`. Any task already registered under this key is cancelled first.
+    pub async fn spawn<F>(&self, key: K, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.cancel(&key);
+
+        let mut set = self.set.lock().await;
+        let abort_handle = set.spawn(task);
+        self.ids.insert(abort_handle.id(), key.clone());
+        self.handles.insert(key, abort_handle);
+    }
+
+    /// Cancels and unregisters the task under `key`, if one is running. Returns whether a task
+    /// was actually found (and therefore cancelled).
+    pub fn cancel(&self, key: &K) -> bool {
+        let Some((_, handle)) = self.handles.remove(key) else {
+            return false;
+        };
+        self.ids.remove(&handle.id());
+        handle.abort();
+        true
+    }
+
+    /// Reclaims the keys of tasks that finished (normally, by panic, or because they were
+    /// cancelled) since the last call. Should be polled periodically, e.g. once per world tick,
+    /// so finished entries don't linger in the map forever.
+    pub async fn reap_finished(&self) {
+        let mut set = self.set.lock().await;
+        while let Some(result) = set.try_join_next_with_id() {
+            let id = match result {
+                Ok((id, ())) => id,
+                Err(join_error) => {
+                    let id = join_error.id();
+                    if join_error.is_panic() {
+                        if let Some(key) = self.ids.get(&id) {
+                            log::error!("Keyed task for {:?} panicked: {}", key.value(), join_error);
+                        }
+                    }
+                    id
+                }
+            };
+            if let Some((_, key)) = self.ids.remove(&id) {
+                self.handles.remove(&key);
+            }
+        }
+    }
+
+    /// Currently live keys (tasks that haven't finished and haven't been reaped yet).
+    pub fn keys(&self) -> Vec<K> {
+        self.handles
+            .iter()
+            .filter(|entry| !entry.value().is_finished())
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// Cancels every registered task and waits for them all to actually stop, e.g. during world
+    /// unload.
+    pub async fn shutdown(&self) {
+        for entry in self.handles.iter() {
+            entry.value().abort();
+        }
+        self.handles.clear();
+        self.ids.clear();
+
+        let mut set = self.set.lock().await;
+        while set.join_next().await.is_some() {}
+    }
+}
+
+impl<K> Default for KeyedTaskMap<K>
+where
+    K: Eq + Hash + Clone + Debug + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}